@@ -16,39 +16,232 @@ pub struct DirectedRelation {
 /// - Normalisation des positions
 ///
 /// Les phases 1 (pré-processing) et 3 (post-processing) sont gérées en externe.
+///
+/// En interne, les entités sont indexées par `usize` (voir `index`/`names`) et les distances/
+/// prédécesseurs vivent dans des matrices denses `Vec<Option<_>>` de taille n×n plutôt que dans
+/// des `HashMap` clé-string: ça évite un `format!` et trois lookups de hachage par triplet (i,j,k)
+/// dans la boucle chaude de `update_distances`. Les méthodes `#[napi]` gardent des signatures
+/// String et font la traduction à la frontière.
+#[derive(Default)]
 #[napi]
 pub struct LayerClassifier {
     relations: Vec<DirectedRelation>,
-    entities: HashSet<String>,
-    distances: HashMap<String, i32>,
+    /// Nom de chaque entité, indexé par son id.
+    names: Vec<String>,
+    /// Id stable attribué à chaque entité lors de sa première insertion.
+    index: HashMap<String, usize>,
+    /// Matrice n×n aplatie: `distances[i * n + j]` est la distance MAX connue de `i` vers `j`.
+    distances: Vec<Option<i32>>,
+    /// Matrice n×n aplatie: `predecessors[i * n + j]` est le nœud intermédiaire `k` qui a produit
+    /// `distances[i * n + j]` (ou `None` si la relation est directe/atomique, ou absente).
+    predecessors: Vec<Option<usize>>,
+    /// Active la variante rayon de `update_distances` (nécessite la feature `parallel`).
+    /// Désactivé par défaut: en dessous de quelques dizaines d'entités, le coût de
+    /// répartition du travail dépasse le gain, donc la version sérielle reste plus rapide.
+    parallel: bool,
+    /// `true` tant que la fermeture transitive n'a pas été recalculée depuis la dernière
+    /// relation ajoutée. Inspiré de `TransitiveRelation` dans rustc: on évite de refaire
+    /// tout Floyd-Warshall à chaque `add_relation` et on diffère le recalcul complet jusqu'à
+    /// la première lecture (`compute_layers`, `get_stats`, `get_longest_path`, `detect_cycles`).
+    dirty: bool,
 }
 
 #[napi]
 impl LayerClassifier {
     #[napi(constructor)]
     pub fn new() -> Self {
-        Self {
-            relations: Vec::new(),
-            entities: HashSet::new(),
-            distances: HashMap::new(),
-        }
+        Self::default()
+    }
+
+    /// Active ou désactive la parallélisation rayon de `update_distances`.
+    ///
+    /// Sans la feature `parallel`, ce toggle n'a aucun effet: l'addon reste purement sériel.
+    #[napi]
+    pub fn set_parallel(&mut self, enabled: bool) {
+        self.parallel = enabled;
     }
 
     /// Ajoute une relation A r B (A doit être à gauche de B)
+    ///
+    /// Ne recalcule pas la fermeture transitive immédiatement: l'arête brute est stockée et le
+    /// graphe est marqué `dirty`, le Floyd-Warshall complet n'étant relancé qu'à la première
+    /// lecture. Pour insérer beaucoup d'arêtes d'un coup, préférer `add_relations`.
     #[napi]
     pub fn add_relation(&mut self, left: String, right: String) {
+        self.insert_raw_edge(left, right, 1);
+        self.dirty = true;
+    }
+
+    /// Comme `add_relation`, mais laisse l'appelant fixer l'écart minimum (en nombre de layers)
+    /// que la relation doit imposer entre `left` et `right`, au lieu du gap unitaire par défaut.
+    ///
+    /// `min_gap` doit être `>= 1`: c'est le pendant "distance initiale" du poids d'une arête, et
+    /// un poids nul ou négatif n'a pas de sens pour une relation "A doit être avant B". Le MAX
+    /// Floyd-Warshall propage ensuite ce poids exactement comme un hop unitaire, donc un cycle
+    /// passant par une arête pondérée positive reste détecté par `detect_cycles` de la même façon.
+    #[napi]
+    pub fn add_relation_weighted(&mut self, left: String, right: String, min_gap: i32) -> Result<()> {
+        if min_gap < 1 {
+            return Err(Error::from_reason(format!(
+                "min_gap doit être >= 1, reçu {}",
+                min_gap
+            )));
+        }
+
+        self.insert_raw_edge(left, right, min_gap);
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Insère un lot de relations et ne recalcule la fermeture transitive qu'une seule fois,
+    /// au lieu d'un Floyd-Warshall complet par arête.
+    #[napi]
+    pub fn add_relations(&mut self, edges: Vec<DirectedRelation>) {
+        for edge in edges {
+            self.insert_raw_edge(edge.left, edge.right, 1);
+        }
+        self.update_distances();
+        self.dirty = false;
+    }
+
+    /// Ajoute une relation et met à jour la fermeture transitive en O(n²) au lieu de relancer
+    /// Floyd-Warshall en entier, en supposant que la matrice est déjà convergée.
+    ///
+    /// Pour une nouvelle arête `left -> right` de poids `w`, tout nouveau plus long chemin passe
+    /// nécessairement par cette arête: `dist(i, j) = max(dist(i, j), dist(i, left) + w + dist(right, j))`
+    /// pour toutes les paires `(i, j)`, avec `dist(x, x) = 0`. Comme le reste de la matrice était
+    /// déjà convergé, il n'y a rien d'autre à relaxer.
+    #[napi]
+    pub fn add_relation_incremental(&mut self, left: String, right: String) {
+        self.ensure_fresh();
+
+        let weight = 1;
+        // `insert_raw_edge` applique déjà le MAX sur la cellule directe (et réinitialise son
+        // prédécesseur si elle gagne), donc il n'y a rien de plus à faire pour l'arête elle-même.
+        self.insert_raw_edge(left.clone(), right.clone(), weight);
+
+        let u = self.entity_id(&left);
+        let v = self.entity_id(&right);
+        let n = self.names.len();
+
+        // Instantanés car on relit ces valeurs pour chaque (i, j) pendant qu'on mute la matrice.
+        let dist_to_u: Vec<Option<i32>> = (0..n)
+            .map(|i| if i == u { Some(0) } else { self.distances[i * n + u] })
+            .collect();
+        let dist_from_v: Vec<Option<i32>> = (0..n)
+            .map(|j| if j == v { Some(0) } else { self.distances[v * n + j] })
+            .collect();
+
+        for (i, &maybe_dist_iu) in dist_to_u.iter().enumerate() {
+            let dist_iu = match maybe_dist_iu {
+                Some(d) => d,
+                None => continue,
+            };
+
+            for (j, &maybe_dist_vj) in dist_from_v.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+
+                let dist_vj = match maybe_dist_vj {
+                    Some(d) => d,
+                    None => continue,
+                };
+
+                let via_new_edge = dist_iu + weight + dist_vj;
+                let idx_ij = i * n + j;
+
+                let better = match self.distances[idx_ij] {
+                    Some(current) => via_new_edge > current,
+                    None => true,
+                };
+
+                if better {
+                    self.distances[idx_ij] = Some(via_new_edge);
+                    // i -> j se décompose en i -> u (inchangé) puis u -> j; et u -> j lui-même
+                    // en u -> v (l'arête neuve) puis v -> j (inchangé).
+                    self.predecessors[idx_ij] = Some(if i == u { v } else { u });
+                }
+            }
+        }
+
+        self.dirty = false;
+    }
+
+    /// Enregistre une arête brute (relation + distance atomique initiale `weight`) sans toucher
+    /// à la fermeture transitive; c'est aux appelants de recalculer (`update_distances`) ou de
+    /// marquer `dirty` selon la stratégie voulue.
+    ///
+    /// Comme le reste de l'algorithme, la cellule directe est mise à jour par MAX plutôt
+    /// qu'écrasée: sans ça, une relation pondérée plus stricte (`add_relation_weighted(a, b, 5)`)
+    /// pourrait être silencieusement ramenée à 1 par un `add_relation(a, b)` ultérieur sur la
+    /// même paire.
+    fn insert_raw_edge(&mut self, left: String, right: String, weight: i32) {
         self.relations.push(DirectedRelation {
             left: left.clone(),
             right: right.clone(),
         });
-        self.entities.insert(left.clone());
-        self.entities.insert(right.clone());
 
-        // Distance initiale = 1 (relation atomique)
-        self.distances.insert(Self::make_key(&left, &right), 1);
+        let left_id = self.entity_id(&left);
+        let right_id = self.entity_id(&right);
 
-        // Recalculer toutes les distances avec les intercalations
-        self.update_distances();
+        // Distance initiale = weight (1 pour une relation atomique non pondérée), sans jamais
+        // rétrograder une distance déjà convergée à une valeur plus faible.
+        let idx = self.cell(left_id, right_id);
+        let should_update = match self.distances[idx] {
+            Some(current) => weight > current,
+            None => true,
+        };
+        if should_update {
+            self.distances[idx] = Some(weight);
+            self.predecessors[idx] = None;
+        }
+    }
+
+    /// Relance Floyd-Warshall si des relations ont été ajoutées depuis le dernier calcul.
+    fn ensure_fresh(&mut self) {
+        if self.dirty {
+            self.update_distances();
+            self.dirty = false;
+        }
+    }
+
+    /// Retourne l'id existant d'une entité ou lui en attribue un nouveau, en faisant grandir
+    /// les matrices `distances`/`predecessors` si besoin.
+    fn entity_id(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.index.get(name) {
+            return id;
+        }
+
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.index.insert(name.to_string(), id);
+        self.grow_to(self.names.len());
+        id
+    }
+
+    /// Agrandit les matrices aplaties à `new_n` × `new_n`, en préservant les valeurs existantes.
+    /// Appelé juste après avoir poussé une nouvelle entité dans `names`, donc `new_n` est
+    /// toujours `previous_n + 1`.
+    fn grow_to(&mut self, new_n: usize) {
+        let previous_n = new_n - 1;
+        let mut new_distances = vec![None; new_n * new_n];
+        let mut new_predecessors = vec![None; new_n * new_n];
+
+        for i in 0..previous_n {
+            for j in 0..previous_n {
+                new_distances[i * new_n + j] = self.distances[i * previous_n + j];
+                new_predecessors[i * new_n + j] = self.predecessors[i * previous_n + j];
+            }
+        }
+
+        self.distances = new_distances;
+        self.predecessors = new_predecessors;
+    }
+
+    /// Index plat `i * n + j` dans les matrices courantes.
+    fn cell(&self, i: usize, j: usize) -> usize {
+        i * self.names.len() + j
     }
 
     /// Met à jour les distances en détectant les intercalations transitives (Théorème de Thalès)
@@ -63,7 +256,20 @@ impl LayerClassifier {
     /// - Pire cas: O(n³) où n = nombre d'entités
     /// - Meilleur cas: O(n² × k) où k = nombre d'itérations avant convergence
     fn update_distances(&mut self) {
-        let max_iterations = self.entities.len();
+        #[cfg(feature = "parallel")]
+        {
+            if self.parallel {
+                self.update_distances_parallel();
+                return;
+            }
+        }
+
+        self.update_distances_serial();
+    }
+
+    fn update_distances_serial(&mut self) {
+        let n = self.names.len();
+        let max_iterations = n;
         let mut iteration = 0;
 
         while iteration < max_iterations {
@@ -71,38 +277,41 @@ impl LayerClassifier {
             let mut changed_in_pass = false;
 
             // Floyd-Warshall: pour chaque nœud intermédiaire k
-            let entities_vec: Vec<String> = self.entities.iter().cloned().collect();
-
-            for k in &entities_vec {
-                for i in &entities_vec {
-                    for j in &entities_vec {
-                        if i != j && i != k && j != k {
-                            // Si on a un chemin i -> k et k -> j
-                            let key_ik = Self::make_key(i, k);
-                            let key_kj = Self::make_key(k, j);
-                            let key_ij = Self::make_key(i, j);
-
-                            if let (Some(&dist_ik), Some(&dist_kj)) =
-                                (self.distances.get(&key_ik), self.distances.get(&key_kj)) {
-                                // Distance via k (principe d'atomicité)
-                                let dist_via_k = dist_ik + dist_kj;
-
-                                // Mettre à jour la distance i -> j si on trouve un chemin plus long (MAX)
-                                match self.distances.get(&key_ij) {
-                                    Some(&current_dist) => {
-                                        if dist_via_k > current_dist {
-                                            // Principe de maximalité: le chemin long l'emporte
-                                            self.distances.insert(key_ij, dist_via_k);
-                                            changed_in_pass = true;
-                                        }
-                                    }
-                                    None => {
-                                        // Créer une nouvelle distance transitive (Thalès inversé)
-                                        self.distances.insert(key_ij, dist_via_k);
-                                        changed_in_pass = true;
-                                    }
-                                }
-                            }
+            for k in 0..n {
+                for i in 0..n {
+                    if i == k {
+                        continue;
+                    }
+
+                    let dist_ik = match self.distances[i * n + k] {
+                        Some(d) => d,
+                        None => continue,
+                    };
+
+                    for j in 0..n {
+                        if i == j || j == k {
+                            continue;
+                        }
+
+                        let dist_kj = match self.distances[k * n + j] {
+                            Some(d) => d,
+                            None => continue,
+                        };
+
+                        // Distance via k (principe d'atomicité)
+                        let dist_via_k = dist_ik + dist_kj;
+                        let idx_ij = i * n + j;
+
+                        let should_update = match self.distances[idx_ij] {
+                            Some(current_dist) => dist_via_k > current_dist,
+                            None => true,
+                        };
+
+                        if should_update {
+                            // Principe de maximalité: le chemin long l'emporte
+                            self.distances[idx_ij] = Some(dist_via_k);
+                            self.predecessors[idx_ij] = Some(k);
+                            changed_in_pass = true;
                         }
                     }
                 }
@@ -116,11 +325,88 @@ impl LayerClassifier {
         }
     }
 
+    /// Variante rayon de `update_distances_serial`.
+    ///
+    /// Pour un `k` fixé, chaque relaxation (i, j) ne lit que la ligne `k` et la colonne `k` et
+    /// n'écrit que la cellule (i, j): les lignes `i` sont donc indépendantes entre elles et on
+    /// peut les relaxer en parallèle avec `par_chunks_mut`. On prend un instantané de la ligne
+    /// et de la colonne `k` avant de lancer le split, puisque la ligne `k` elle-même n'est pas
+    /// mutée à cette passe (on saute `i == k`) mais reste lue par toutes les autres lignes.
+    /// Les flags `changed` par ligne sont combinés par réduction pour préserver le pruning
+    /// précoce (arrêt dès qu'une passe complète ne change plus rien).
+    #[cfg(feature = "parallel")]
+    fn update_distances_parallel(&mut self) {
+        use rayon::prelude::*;
+
+        let n = self.names.len();
+        let max_iterations = n;
+        let mut iteration = 0;
+
+        while iteration < max_iterations {
+            iteration += 1;
+            let mut changed_in_pass = false;
+
+            for k in 0..n {
+                let row_k: Vec<Option<i32>> = (0..n).map(|j| self.distances[k * n + j]).collect();
+                let col_k: Vec<Option<i32>> = (0..n).map(|i| self.distances[i * n + k]).collect();
+
+                let pass_changed = self
+                    .distances
+                    .par_chunks_mut(n)
+                    .zip(self.predecessors.par_chunks_mut(n))
+                    .enumerate()
+                    .map(|(i, (dist_row, pred_row))| {
+                        if i == k {
+                            return false;
+                        }
+
+                        let dist_ik = match col_k[i] {
+                            Some(d) => d,
+                            None => return false,
+                        };
+
+                        let mut row_changed = false;
+                        for j in 0..n {
+                            if i == j || j == k {
+                                continue;
+                            }
+
+                            let dist_kj = match row_k[j] {
+                                Some(d) => d,
+                                None => continue,
+                            };
+
+                            let dist_via_k = dist_ik + dist_kj;
+                            let should_update = match dist_row[j] {
+                                Some(current_dist) => dist_via_k > current_dist,
+                                None => true,
+                            };
+
+                            if should_update {
+                                dist_row[j] = Some(dist_via_k);
+                                pred_row[j] = Some(k);
+                                row_changed = true;
+                            }
+                        }
+
+                        row_changed
+                    })
+                    .reduce(|| false, |a, b| a || b);
+
+                changed_in_pass |= pass_changed;
+            }
+
+            if !changed_in_pass {
+                break;
+            }
+        }
+    }
+
     /// Compte le nombre de connexions pour chaque entité
     fn count_connections(&self) -> HashMap<String, usize> {
         let mut connections = HashMap::new();
 
-        for entity in &self.entities {
+        for entity in &self.names {
             let mut count = 0;
             for rel in &self.relations {
                 if &rel.left == entity || &rel.right == entity {
@@ -133,18 +419,91 @@ impl LayerClassifier {
         connections
     }
 
+    /// Détecte les cycles de relations contradictoires.
+    ///
+    /// Analogue MAX du cycle négatif en plus-court-chemin. Deux façons pour un cycle de se
+    /// manifester ici:
+    /// - une auto-boucle directe (`add_relation("A", "A")` ou une variante pondérée): le triple
+    ///   loop de `update_distances` exclut volontairement `i == j`, donc la diagonale
+    ///   `distances[x * n + x]` n'est jamais mise à jour par Floyd-Warshall lui-même, mais elle
+    ///   peut être écrite directement par `insert_raw_edge`. On l'inspecte donc explicitement:
+    ///   toute valeur positive sur la diagonale prouve un cycle à travers `x`.
+    /// - un cycle transitif classique (A -> B -> ... -> A, sans auto-boucle directe): on le
+    ///   retrouve en cherchant, pour chaque entité `x`, un nœud `k` tel que `dist(x, k)` et
+    ///   `dist(k, x)` existent tous les deux - leur somme est la longueur du cycle `x -> k -> x`,
+    ///   et comme tous les poids sont positifs, son existence prouve à elle seule une
+    ///   contradiction (A doit être à la fois avant et après B).
+    #[napi]
+    pub fn detect_cycles(&mut self) -> Vec<Vec<String>> {
+        self.ensure_fresh();
+
+        let n = self.names.len();
+        let mut seen = HashSet::new();
+        let mut cycles = Vec::new();
+
+        for x in 0..n {
+            if let Some(d) = self.distances[x * n + x] {
+                if d > 0 {
+                    let cycle = self.build_path_ids(x, x);
+
+                    let mut signature = cycle.clone();
+                    signature.sort();
+                    signature.dedup();
+                    if seen.insert(signature) {
+                        cycles.push(cycle.into_iter().map(|id| self.names[id].clone()).collect());
+                    }
+                }
+            }
+        }
+
+        for x in 0..n {
+            for k in 0..n {
+                if x == k {
+                    continue;
+                }
+
+                if self.distances[x * n + k].is_some() && self.distances[k * n + x].is_some() {
+                    let mut cycle = self.build_path_ids(x, k);
+                    cycle.pop();
+                    cycle.extend(self.build_path_ids(k, x));
+
+                    let mut signature = cycle.clone();
+                    signature.sort();
+                    signature.dedup();
+                    if seen.insert(signature) {
+                        cycles.push(cycle.into_iter().map(|id| self.names[id].clone()).collect());
+                    }
+                }
+            }
+        }
+
+        cycles
+    }
+
     /// Calcule les layers en utilisant l'entité la plus connectée comme référence
     ///
     /// Processus:
-    /// 1. Sélectionner l'entité de référence (avec cascade criteria)
-    /// 2. Placer la référence au layer 0
-    /// 3. Propager les positions en respectant les distances
-    /// 4. Normaliser pour que le minimum soit au layer 0
-    /// 5. Grouper par layer
+    /// 1. Détecter les cycles contradictoires et échouer si il y en a
+    /// 2. Sélectionner l'entité de référence (avec cascade criteria)
+    /// 3. Placer la référence au layer 0
+    /// 4. Propager les positions en respectant les distances
+    /// 5. Normaliser pour que le minimum soit au layer 0
+    /// 6. Grouper par layer
     #[napi]
-    pub fn compute_layers(&self) -> Vec<Vec<String>> {
-        if self.entities.is_empty() {
-            return Vec::new();
+    pub fn compute_layers(&mut self) -> Result<Vec<Vec<String>>> {
+        self.ensure_fresh();
+
+        let cycles = self.detect_cycles();
+        if !cycles.is_empty() {
+            return Err(Error::from_reason(format!(
+                "Relations contradictoires détectées: {} cycle(s), ex. {:?}",
+                cycles.len(),
+                cycles[0]
+            )));
+        }
+
+        if self.names.is_empty() {
+            return Ok(Vec::new());
         }
 
         let connections = self.count_connections();
@@ -169,7 +528,7 @@ impl LayerClassifier {
         let mut reference_entity = String::new();
         let mut best_score = (0, 0);
 
-        for entity in &self.entities {
+        for entity in &self.names {
             let score = get_reference_score(entity);
             if score.0 > best_score.0 || (score.0 == best_score.0 && score.1 > best_score.1) {
                 best_score = score;
@@ -182,46 +541,54 @@ impl LayerClassifier {
             reference_entity, best_score.0, best_score.1
         );
 
+        let n = self.names.len();
+
         // Placer l'entité de référence au layer 0
         let mut layers = HashMap::new();
         layers.insert(reference_entity.clone(), 0);
 
         // Itérer jusqu'à ce que toutes les entités soient placées
-        let max_iterations = self.entities.len().pow(2);
+        let max_iterations = n.pow(2);
         let mut iteration = 0;
 
-        while layers.len() < self.entities.len() && iteration < max_iterations {
+        while layers.len() < n && iteration < max_iterations {
             iteration += 1;
             let mut progress = false;
 
-            for (key, &distance) in &self.distances {
-                let (left, right) = Self::parse_key(key);
-
-                if layers.contains_key(&left) && !layers.contains_key(&right) {
-                    // Placer right pour la première fois
-                    layers.insert(right.clone(), layers[&left] + distance);
-                    progress = true;
-                } else if layers.contains_key(&right) && !layers.contains_key(&left) {
-                    // Placer left pour la première fois
-                    layers.insert(left.clone(), layers[&right] - distance);
-                    progress = true;
-                } else if layers.contains_key(&left) && layers.contains_key(&right) {
-                    // Les deux sont déjà placés - vérifier la cohérence
-                    let expected_right = layers[&left] + distance;
-
-                    if layers[&right] < expected_right {
-                        layers.insert(right.clone(), expected_right);
+            for i in 0..n {
+                for j in 0..n {
+                    let distance = match self.distances[i * n + j] {
+                        Some(d) => d,
+                        None => continue,
+                    };
+                    let left = &self.names[i];
+                    let right = &self.names[j];
+
+                    if layers.contains_key(left) && !layers.contains_key(right) {
+                        // Placer right pour la première fois
+                        layers.insert(right.clone(), layers[left] + distance);
                         progress = true;
+                    } else if layers.contains_key(right) && !layers.contains_key(left) {
+                        // Placer left pour la première fois
+                        layers.insert(left.clone(), layers[right] - distance);
+                        progress = true;
+                    } else if layers.contains_key(left) && layers.contains_key(right) {
+                        // Les deux sont déjà placés - vérifier la cohérence
+                        let expected_right = layers[left] + distance;
+
+                        if layers[right] < expected_right {
+                            layers.insert(right.clone(), expected_right);
+                            progress = true;
+                        }
                     }
                 }
             }
 
             if !progress {
                 // Placer les entités restantes au layer 0
-                for entity in &self.entities {
+                for entity in &self.names {
                     if !layers.contains_key(entity) {
                         layers.insert(entity.clone(), 0);
-                        progress = true;
                     }
                 }
             }
@@ -233,7 +600,7 @@ impl LayerClassifier {
 
         for (entity, &layer) in &layers {
             if entity != &reference_entity {
-                by_distance.entry(layer).or_insert_with(Vec::new).push(entity.clone());
+                by_distance.entry(layer).or_default().push(entity.clone());
             }
         }
 
@@ -270,7 +637,7 @@ impl LayerClassifier {
         // Grouper par layer
         let mut layer_dict: HashMap<i32, Vec<String>> = HashMap::new();
         for (entity, &layer) in &layers {
-            layer_dict.entry(layer).or_insert_with(Vec::new).push(entity.clone());
+            layer_dict.entry(layer).or_default().push(entity.clone());
         }
 
         // Convertir en array trié par index de layer
@@ -284,27 +651,527 @@ impl LayerClassifier {
             sorted_layers.push(layer);
         }
 
-        sorted_layers
+        Ok(sorted_layers)
+    }
+
+    /// Comme `compute_layers`, mais ordonne en plus chaque layer pour limiter les croisements
+    /// entre layers adjacents.
+    ///
+    /// Applique l'heuristique classique du barycentre (Sugiyama et al.): en alternant des
+    /// passes descendantes (layer `i` ordonné par rapport aux positions du layer `i - 1`) et
+    /// montantes (layer `i` par rapport au layer `i + 1`), chaque entité est repositionnée à la
+    /// moyenne des positions de ses voisins dans le layer de référence, puis le layer
+    /// est trié par cette valeur. On répète un nombre fixe de passes et on garde l'ordonnancement
+    /// qui minimise le nombre total de croisements, plutôt que le dernier obtenu.
+    #[napi]
+    pub fn compute_ordered_layers(&mut self) -> Result<Vec<Vec<String>>> {
+        let layers = self.compute_layers()?;
+        if layers.len() <= 1 {
+            return Ok(layers);
+        }
+
+        const ROUNDS: usize = 4;
+
+        let mut best = layers.clone();
+        let mut best_crossings = self.count_crossings(&best);
+        let mut current = layers;
+
+        for round in 0..ROUNDS {
+            // Alterne les sens de balayage pour propager l'information dans les deux directions.
+            let downward = round % 2 == 0;
+            self.barycenter_sweep(&mut current, downward);
+
+            let crossings = self.count_crossings(&current);
+            if crossings < best_crossings {
+                best_crossings = crossings;
+                best = current.clone();
+            }
+        }
+
+        Ok(best)
+    }
+
+    /// Une passe de balayage barycentre sur tous les layers, dans le sens indiqué.
+    fn barycenter_sweep(&self, layers: &mut [Vec<String>], downward: bool) {
+        let indices: Vec<usize> = if downward {
+            (1..layers.len()).collect()
+        } else {
+            (0..layers.len() - 1).rev().collect()
+        };
+
+        for idx in indices {
+            let reference_idx = if downward { idx - 1 } else { idx + 1 };
+            let reference_position: HashMap<&str, usize> = layers[reference_idx]
+                .iter()
+                .enumerate()
+                .map(|(pos, entity)| (entity.as_str(), pos))
+                .collect();
+
+            let mut scored: Vec<(f64, String)> = layers[idx]
+                .iter()
+                .enumerate()
+                .map(|(current_pos, entity)| {
+                    let neighbor_positions: Vec<f64> = self
+                        .neighbors_of(entity)
+                        .into_iter()
+                        .filter_map(|n| reference_position.get(n.as_str()).map(|&p| p as f64))
+                        .collect();
+
+                    let barycenter = if neighbor_positions.is_empty() {
+                        // Aucun voisin dans le layer de référence: on garde la position actuelle
+                        // pour ne pas perturber inutilement l'ordre.
+                        current_pos as f64
+                    } else {
+                        neighbor_positions.iter().sum::<f64>() / neighbor_positions.len() as f64
+                    };
+
+                    (barycenter, entity.clone())
+                })
+                .collect();
+
+            scored.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            layers[idx] = scored.into_iter().map(|(_, entity)| entity).collect();
+        }
+    }
+
+    /// Les voisins directs d'une entité (relations brutes, sans tenir compte des distances).
+    fn neighbors_of(&self, entity: &str) -> Vec<String> {
+        let mut neighbors = Vec::new();
+        for rel in &self.relations {
+            if rel.left == entity {
+                neighbors.push(rel.right.clone());
+            } else if rel.right == entity {
+                neighbors.push(rel.left.clone());
+            }
+        }
+        neighbors
+    }
+
+    /// Compte les croisements d'arêtes entre layers adjacents pour un ordonnancement donné.
+    ///
+    /// Pour chaque paire de layers consécutifs, deux arêtes (p1 -> p1') et (p2 -> p2') se
+    /// croisent si leurs positions s'inversent (`p1 < p2` mais `p1' > p2'`, ou l'inverse). Plutôt
+    /// que la comparaison O(arêtes²) naïve, on trie les arêtes par position haute puis on compte
+    /// les inversions de position basse avec un arbre de Fenwick (BIT), en O(n log n).
+    fn count_crossings(&self, layers: &[Vec<String>]) -> usize {
+        let mut total = 0;
+
+        for idx in 0..layers.len().saturating_sub(1) {
+            let upper_position: HashMap<&str, usize> = layers[idx]
+                .iter()
+                .enumerate()
+                .map(|(pos, entity)| (entity.as_str(), pos))
+                .collect();
+            let lower_position: HashMap<&str, usize> = layers[idx + 1]
+                .iter()
+                .enumerate()
+                .map(|(pos, entity)| (entity.as_str(), pos))
+                .collect();
+
+            let mut edges: Vec<(usize, usize)> = Vec::new();
+            for rel in &self.relations {
+                if let (Some(&pu), Some(&pl)) = (
+                    upper_position.get(rel.left.as_str()),
+                    lower_position.get(rel.right.as_str()),
+                ) {
+                    edges.push((pu, pl));
+                } else if let (Some(&pu), Some(&pl)) = (
+                    upper_position.get(rel.right.as_str()),
+                    lower_position.get(rel.left.as_str()),
+                ) {
+                    edges.push((pu, pl));
+                }
+            }
+
+            edges.sort_by_key(|&(pu, _)| pu);
+            total += Self::count_inversions_bit(&edges, layers[idx + 1].len());
+        }
+
+        total
+    }
+
+    /// Compte, parmi des arêtes triées par position haute (`upper_pos`), combien de paires ont
+    /// une position basse (`lower_pos`) inversée - c'est-à-dire combien de croisements elles
+    /// produisent entre deux layers adjacents.
+    ///
+    /// Implémentation classique "BIT à la demande": on balaie les arêtes dans l'ordre
+    /// d'`upper_pos`, et pour chacune on interroge l'arbre de Fenwick pour savoir combien
+    /// d'arêtes déjà vues ont un `lower_pos` strictement supérieur (= combien se croisent avec
+    /// elle), avant de l'insérer à son tour. Les arêtes à `upper_pos` égal sont traitées par lot
+    /// (toutes interrogées avant d'être insérées) pour ne pas se compter entre elles: à positions
+    /// hautes égales, `p1 < p2` est faux des deux côtés donc aucun croisement n'est possible.
+    fn count_inversions_bit(edges: &[(usize, usize)], lower_len: usize) -> usize {
+        let mut bit = vec![0usize; lower_len + 1];
+        let mut seen = 0usize;
+        let mut total = 0usize;
+
+        let query = |bit: &[usize], mut i: usize| -> usize {
+            let mut sum = 0;
+            while i > 0 {
+                sum += bit[i];
+                i -= i & i.wrapping_neg();
+            }
+            sum
+        };
+        let update = |bit: &mut [usize], mut i: usize| {
+            while i <= lower_len {
+                bit[i] += 1;
+                i += i & i.wrapping_neg();
+            }
+        };
+
+        let mut idx = 0;
+        while idx < edges.len() {
+            let pu = edges[idx].0;
+            let mut batch_end = idx;
+            while batch_end < edges.len() && edges[batch_end].0 == pu {
+                batch_end += 1;
+            }
+
+            // Phase 1: compter contre les arêtes déjà vues (upper_pos strictement plus petit),
+            // sans tenir compte des autres arêtes du même lot.
+            for &(_, lower_pos) in &edges[idx..batch_end] {
+                let position = lower_pos + 1; // BIT 1-indexé
+                let seen_le = query(&bit, position);
+                total += seen - seen_le;
+            }
+
+            // Phase 2: insérer tout le lot une fois les comptages faits.
+            for &(_, lower_pos) in &edges[idx..batch_end] {
+                update(&mut bit, lower_pos + 1);
+                seen += 1;
+            }
+
+            idx = batch_end;
+        }
+
+        total
+    }
+
+    /// Reconstruit la chaîne d'entités intermédiaires du plus long chemin entre `left` et `right`.
+    ///
+    /// Remonte la matrice `predecessors` à la manière de Floyd-Warshall: si la cellule `(left, right)` a un
+    /// prédécesseur `k`, on reconstruit récursivement `left -> k` puis `k -> right` et on fusionne
+    /// les deux chemins. Si aucun prédécesseur n'est enregistré, la relation est directe (ou absente).
+    #[napi]
+    pub fn get_longest_path(&mut self, left: String, right: String) -> Vec<String> {
+        self.ensure_fresh();
+
+        if left == right {
+            return vec![left];
+        }
+
+        let (left_id, right_id) = match (self.index.get(&left), self.index.get(&right)) {
+            (Some(&l), Some(&r)) => (l, r),
+            _ => return Vec::new(),
+        };
+
+        if self.distances[left_id * self.names.len() + right_id].is_none() {
+            return Vec::new();
+        }
+
+        self.build_path_ids(left_id, right_id)
+            .into_iter()
+            .map(|id| self.names[id].clone())
+            .collect()
+    }
+
+    /// Remonte `predecessors` pour reconstruire le chemin `left -> right`.
+    ///
+    /// Sur une matrice convergée, la reconstruction récursive ci-dessous termine en au plus
+    /// `2n - 3` appels (un chemin simple a au plus `n - 1` arêtes, et chaque arête est une
+    /// feuille de l'arbre de décomposition). Mais si `update_distances` n'a pas pu converger à cause d'un cycle de poids
+    /// positifs (le cas que `detect_cycles` signale via un `dist(x, x) > 0` ou une paire
+    /// `dist(x, k)`/`dist(k, x)` simultanée), `predecessors` lui-même peut être cyclique et cette
+    /// récursion ne terminerait jamais - un stack overflow n'étant pas un panic rattrapable, ça
+    /// ferait planter tout le process plutôt que de remonter une erreur. `budget` borne donc le
+    /// nombre total d'appels (partagé entre les deux branches de la reconstruction, pas dupliqué):
+    /// une fois épuisé, on traite le segment restant comme une arête directe au lieu de continuer
+    /// à le décomposer.
+    fn build_path_ids(&self, left: usize, right: usize) -> Vec<usize> {
+        let mut budget = self.names.len().max(1) * self.names.len().max(1);
+        self.build_path_ids_bounded(left, right, &mut budget)
+    }
+
+    fn build_path_ids_bounded(&self, left: usize, right: usize, budget: &mut usize) -> Vec<usize> {
+        if *budget == 0 {
+            return vec![left, right];
+        }
+        *budget -= 1;
+
+        let n = self.names.len();
+        match self.predecessors[left * n + right] {
+            Some(k) => {
+                let mut path = self.build_path_ids_bounded(left, k, budget);
+                path.pop();
+                path.extend(self.build_path_ids_bounded(k, right, budget));
+                path
+            }
+            None => vec![left, right],
+        }
     }
 
     /// Getter pour les statistiques
     #[napi]
-    pub fn get_stats(&self) -> serde_json::Value {
+    pub fn get_stats(&mut self) -> serde_json::Value {
+        self.ensure_fresh();
+
         serde_json::json!({
-            "entities": self.entities.len(),
+            "entities": self.names.len(),
             "relations": self.relations.len(),
-            "distances": self.distances.len(),
+            "distances": self.distances.iter().filter(|d| d.is_some()).count(),
         })
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dist(c: &mut LayerClassifier, left: &str, right: &str) -> Option<i32> {
+        c.ensure_fresh();
+        let l = *c.index.get(left).unwrap();
+        let r = *c.index.get(right).unwrap();
+        c.distances[c.cell(l, r)]
+    }
+
+    #[test]
+    fn add_relation_does_not_collapse_a_wider_weighted_constraint() {
+        let mut c = LayerClassifier::new();
+        c.add_relation_weighted("A".to_string(), "B".to_string(), 5).unwrap();
+        assert_eq!(dist(&mut c, "A", "B"), Some(5));
+
+        // Une relation non pondérée ultérieure sur la même paire ne doit pas rétrograder
+        // la distance déjà convergée à 5.
+        c.add_relation("A".to_string(), "B".to_string());
+        assert_eq!(dist(&mut c, "A", "B"), Some(5));
+    }
+
+    #[test]
+    fn add_relations_batch_keeps_the_wider_weighted_constraint() {
+        let mut c = LayerClassifier::new();
+        c.add_relation_weighted("A".to_string(), "B".to_string(), 5).unwrap();
+        c.add_relations(vec![DirectedRelation {
+            left: "A".to_string(),
+            right: "B".to_string(),
+        }]);
+        assert_eq!(dist(&mut c, "A", "B"), Some(5));
+    }
+
+    #[test]
+    fn detect_cycles_catches_a_direct_self_loop() {
+        let mut c = LayerClassifier::new();
+        c.add_relation("A".to_string(), "B".to_string());
+        c.add_relation("A".to_string(), "A".to_string());
+
+        let cycles = c.detect_cycles();
+        assert!(!cycles.is_empty());
+        assert!(cycles.iter().any(|cycle| cycle.contains(&"A".to_string())));
+    }
+
+    #[test]
+    fn detect_cycles_still_catches_transitive_cycles() {
+        let mut c = LayerClassifier::new();
+        c.add_relation("A".to_string(), "B".to_string());
+        c.add_relation("B".to_string(), "A".to_string());
+
+        assert!(!c.detect_cycles().is_empty());
+    }
+
+    #[test]
+    fn a_longer_transitive_cycle_does_not_crash_detect_cycles_or_compute_layers() {
+        // Régression: sur un cycle de 3+ nœuds, `update_distances` ne converge jamais (les
+        // distances MAX croissent à chaque passe jusqu'au cap `max_iterations`), ce qui laisse
+        // `predecessors` lui-même cyclique. `detect_cycles`/`compute_layers` ne doivent pas
+        // planter (stack overflow via `build_path_ids`) en reconstruisant un chemin à travers
+        // ce cycle - ils doivent toujours retourner une réponse.
+        let mut c = LayerClassifier::new();
+        c.add_relation("A".to_string(), "B".to_string());
+        c.add_relation("B".to_string(), "C".to_string());
+        c.add_relation("C".to_string(), "A".to_string());
+
+        assert!(!c.detect_cycles().is_empty());
+        assert!(c.compute_layers().is_err());
+    }
+
+    #[test]
+    fn detect_cycles_reports_nothing_on_an_acyclic_graph() {
+        let mut c = LayerClassifier::new();
+        c.add_relation("A".to_string(), "B".to_string());
+        c.add_relation("B".to_string(), "C".to_string());
+
+        assert!(c.detect_cycles().is_empty());
+    }
+
+    #[test]
+    fn compute_layers_rejects_a_self_loop_cycle() {
+        let mut c = LayerClassifier::new();
+        c.add_relation("A".to_string(), "B".to_string());
+        c.add_relation("A".to_string(), "A".to_string());
 
-    /// Helper: créer une clé unique pour une paire (left, right)
-    fn make_key(left: &str, right: &str) -> String {
-        format!("{}→{}", left, right)
+        assert!(c.compute_layers().is_err());
     }
 
-    /// Helper: parser une clé pour récupérer (left, right)
-    fn parse_key(key: &str) -> (String, String) {
-        let parts: Vec<&str> = key.split('→').collect();
-        (parts[0].to_string(), parts[1].to_string())
+    #[test]
+    fn add_relation_incremental_matches_a_full_recompute() {
+        let mut incremental = LayerClassifier::new();
+        incremental.add_relations(vec![
+            DirectedRelation { left: "A".to_string(), right: "B".to_string() },
+            DirectedRelation { left: "B".to_string(), right: "C".to_string() },
+        ]);
+        incremental.add_relation_incremental("A".to_string(), "C".to_string());
+
+        let mut batch = LayerClassifier::new();
+        batch.add_relations(vec![
+            DirectedRelation { left: "A".to_string(), right: "B".to_string() },
+            DirectedRelation { left: "B".to_string(), right: "C".to_string() },
+            DirectedRelation { left: "A".to_string(), right: "C".to_string() },
+        ]);
+
+        let incremental_dist = dist(&mut incremental, "A", "C");
+        assert_eq!(incremental_dist, Some(2));
+        assert_eq!(incremental_dist, dist(&mut batch, "A", "C"));
+    }
+
+    #[test]
+    fn add_relations_batch_computes_the_transitive_closure_once() {
+        let mut c = LayerClassifier::new();
+        c.add_relations(vec![
+            DirectedRelation { left: "A".to_string(), right: "B".to_string() },
+            DirectedRelation { left: "B".to_string(), right: "C".to_string() },
+        ]);
+
+        assert_eq!(dist(&mut c, "A", "C"), Some(2));
+    }
+
+    #[test]
+    fn get_longest_path_reconstructs_the_full_chain() {
+        let mut c = LayerClassifier::new();
+        c.add_relation("A".to_string(), "B".to_string());
+        c.add_relation("B".to_string(), "C".to_string());
+
+        assert_eq!(
+            c.get_longest_path("A".to_string(), "C".to_string()),
+            vec!["A".to_string(), "B".to_string(), "C".to_string()]
+        );
+    }
+
+    #[test]
+    fn get_longest_path_returns_empty_when_no_relation_exists() {
+        let mut c = LayerClassifier::new();
+        c.add_relation("A".to_string(), "B".to_string());
+        c.add_relation("X".to_string(), "Y".to_string());
+
+        assert!(c.get_longest_path("A".to_string(), "Y".to_string()).is_empty());
+    }
+
+    #[test]
+    fn get_longest_path_from_an_entity_to_itself_is_a_single_element() {
+        let mut c = LayerClassifier::new();
+        c.add_relation("A".to_string(), "B".to_string());
+
+        assert_eq!(
+            c.get_longest_path("A".to_string(), "A".to_string()),
+            vec!["A".to_string()]
+        );
+    }
+
+    #[test]
+    fn repeated_entities_reuse_the_same_id_instead_of_growing_the_matrix() {
+        let mut c = LayerClassifier::new();
+        c.add_relation("A".to_string(), "B".to_string());
+        c.add_relation("A".to_string(), "C".to_string());
+        c.add_relation("B".to_string(), "C".to_string());
+
+        // "A", "B", "C" ne doivent être enregistrées qu'une fois chacune, quel que soit le
+        // nombre de relations qui les référencent - sinon la matrice n×n grandirait sans borne.
+        assert_eq!(c.names.len(), 3);
+        assert_eq!(c.distances.len(), 9);
+    }
+
+    #[test]
+    fn the_matrix_grows_correctly_as_entities_are_added_one_relation_at_a_time() {
+        let mut incremental = LayerClassifier::new();
+        incremental.add_relation("A".to_string(), "B".to_string());
+        incremental.add_relation("B".to_string(), "C".to_string());
+        incremental.add_relation("C".to_string(), "D".to_string());
+
+        let mut batch = LayerClassifier::new();
+        batch.add_relations(vec![
+            DirectedRelation { left: "A".to_string(), right: "B".to_string() },
+            DirectedRelation { left: "B".to_string(), right: "C".to_string() },
+            DirectedRelation { left: "C".to_string(), right: "D".to_string() },
+        ]);
+
+        let incremental_dist = dist(&mut incremental, "A", "D");
+        assert_eq!(incremental_dist, Some(3));
+        assert_eq!(incremental_dist, dist(&mut batch, "A", "D"));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn set_parallel_true_matches_the_serial_closure() {
+        fn edges() -> Vec<DirectedRelation> {
+            vec![
+                DirectedRelation { left: "A".to_string(), right: "B".to_string() },
+                DirectedRelation { left: "B".to_string(), right: "C".to_string() },
+                DirectedRelation { left: "A".to_string(), right: "D".to_string() },
+                DirectedRelation { left: "D".to_string(), right: "C".to_string() },
+            ]
+        }
+
+        let mut serial = LayerClassifier::new();
+        serial.add_relations(edges());
+
+        let mut parallel = LayerClassifier::new();
+        parallel.set_parallel(true);
+        parallel.add_relations(edges());
+
+        assert_eq!(parallel.distances, serial.distances);
+    }
+
+    #[test]
+    fn count_crossings_detects_a_single_inversion() {
+        let mut c = LayerClassifier::new();
+        c.add_relation("A".to_string(), "Y".to_string());
+        c.add_relation("B".to_string(), "X".to_string());
+
+        let layers = vec![
+            vec!["A".to_string(), "B".to_string()],
+            vec!["X".to_string(), "Y".to_string()],
+        ];
+
+        assert_eq!(c.count_crossings(&layers), 1);
+    }
+
+    #[test]
+    fn count_crossings_is_zero_when_edges_do_not_cross() {
+        let mut c = LayerClassifier::new();
+        c.add_relation("A".to_string(), "X".to_string());
+        c.add_relation("B".to_string(), "Y".to_string());
+
+        let layers = vec![
+            vec!["A".to_string(), "B".to_string()],
+            vec!["X".to_string(), "Y".to_string()],
+        ];
+
+        assert_eq!(c.count_crossings(&layers), 0);
+    }
+
+    #[test]
+    fn compute_ordered_layers_reduces_crossings_below_the_naive_order() {
+        let mut c = LayerClassifier::new();
+        c.add_relation("H".to_string(), "A".to_string());
+        c.add_relation("H".to_string(), "B".to_string());
+        c.add_relation("A".to_string(), "Y".to_string());
+        c.add_relation("B".to_string(), "X".to_string());
+
+        // Ordre naïf (alphabétique): A->Y et B->X se croisent.
+        let naive = c.compute_layers().unwrap();
+        assert_eq!(c.count_crossings(&naive), 1);
+
+        // L'heuristique du barycentre doit trouver la permutation sans croisement.
+        let ordered = c.compute_ordered_layers().unwrap();
+        assert_eq!(c.count_crossings(&ordered), 0);
     }
 }